@@ -0,0 +1,100 @@
+// Streaming CRC-32 (ISO-3309 / gzip) accumulator.
+pub struct Crc32 {
+	crc: u32,
+}
+
+impl Crc32 {
+	pub fn new() -> Crc32 {
+		Crc32 { crc: 0xFFFFFFFF }
+	}
+
+	pub fn update(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.crc ^= byte as u32;
+			for _ in 0..8 {
+				let mask = (self.crc & 1).wrapping_neg();
+				self.crc = (self.crc >> 1) ^ (0xEDB88320 & mask);
+			}
+		}
+	}
+
+	pub fn finish(&self) -> u32 {
+		!self.crc
+	}
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+	let mut crc = Crc32::new();
+	crc.update(data);
+	crc.finish()
+}
+
+// Streaming Adler-32 (zlib) accumulator.
+pub struct Adler32 {
+	a: u32,
+	b: u32,
+}
+
+const ADLER_MOD: u32 = 65521;
+
+impl Adler32 {
+	pub fn new() -> Adler32 {
+		Adler32 { a: 1, b: 0 }
+	}
+
+	pub fn update(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.a = (self.a + byte as u32) % ADLER_MOD;
+			self.b = (self.b + self.a) % ADLER_MOD;
+		}
+	}
+
+	pub fn finish(&self) -> u32 {
+		(self.b << 16) | self.a
+	}
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+	let mut adler = Adler32::new();
+	adler.update(data);
+	adler.finish()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn crc32_matches_known_check_value() {
+		// the standard CRC-32 check value for the ASCII string "123456789"
+		assert_eq!(crc32(b"123456789"), 0xCBF43926);
+		assert_eq!(crc32(b""), 0);
+	}
+
+	#[test]
+	fn crc32_streaming_matches_one_shot() {
+		let data = b"the quick brown fox jumps over the lazy dog";
+		let mut streaming = Crc32::new();
+		for chunk in data.chunks(3) {
+			streaming.update(chunk);
+		}
+		assert_eq!(streaming.finish(), crc32(data));
+	}
+
+	#[test]
+	fn adler32_matches_known_check_value() {
+		// "Wikipedia" -> 0x11E60398 is the commonly cited Adler-32 example
+		assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+		assert_eq!(adler32(b""), 1);
+	}
+
+	#[test]
+	fn adler32_streaming_matches_one_shot() {
+		let data = b"the quick brown fox jumps over the lazy dog";
+		let mut streaming = Adler32::new();
+		for chunk in data.chunks(3) {
+			streaming.update(chunk);
+		}
+		assert_eq!(streaming.finish(), adler32(data));
+	}
+}
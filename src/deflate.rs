@@ -1,10 +1,13 @@
-use std::io::Write;
+use std::io::{self, Write};
 
+use crate::checksum;
 use crate::huffman;
 mod lempel_ziv;
 mod block_splitter;
 use block_splitter::Block;
+use lempel_ziv::WINDOW_SIZE;
 
+#[derive(Clone, Copy)]
 pub enum Token {
 	Literal(u8),
 	Repeat(u32, u32),
@@ -12,26 +15,208 @@ pub enum Token {
 
 pub fn deflate<T: Write>(file: &[u8], out: &mut T) {
 	let tokens = lempel_ziv::lempel_ziv(file);
-	let blocks = block_splitter::block_split(&tokens);
+	let blocks = block_splitter::block_split(&[], &tokens);
 	let mut writer = DeflateWriter::new(out);
+	write_blocks(&mut writer, &blocks, true).unwrap();
+	writer.finish().unwrap();
+}
 
+// Writes each block to `writer`. `final_stream` marks the very last block as
+// BFINAL; passing `false` leaves the stream open for more blocks to follow,
+// which is how `Compressor` emits blocks as they fill.
+fn write_blocks<T: Write>(writer: &mut DeflateWriter<T>, blocks: &[Block], final_stream: bool) -> io::Result<()> {
 	for (i, block) in blocks.iter().enumerate() {
-		let is_last = i == blocks.len() - 1;
+		let is_last = final_stream && i == blocks.len() - 1;
 		match block {
 			Block::FixedCodes { tokens } => {
-				writer.new_fixed_codes_block(is_last);
-				tokens.iter().for_each(|t| writer.write(t));
+				writer.new_fixed_codes_block(is_last)?;
+				tokens.iter().try_for_each(|t| writer.write(t))?;
 			}
 			Block::DynamicCodes { tokens, literal_code_lens, distance_code_lens } => {
-				writer.new_dynamic_codes_block(is_last, literal_code_lens, distance_code_lens);
-				tokens.iter().for_each(|t| writer.write(t));
+				writer.new_dynamic_codes_block(is_last, literal_code_lens, distance_code_lens)?;
+				tokens.iter().try_for_each(|t| writer.write(t))?;
+			}
+			Block::Stored { data } => {
+				writer.new_stored_block(is_last, data)?;
 			}
 		}
 	}
+	Ok(())
+}
+
+// 10-byte gzip header (RFC 1952): magic, CM, FLG, MTIME, XFL, OS. Shared
+// between the whole-buffer `gzip` and the streaming `Compressor::gzip`.
+const GZIP_HEADER: [u8; 10] = [
+	0x1F, 0x8B, // magic
+	8, // CM = deflate
+	0, // FLG
+	0, 0, 0, 0, // MTIME (unknown)
+	0, // XFL
+	0xFF, // OS (unknown)
+];
+
+// 2-byte CMF/FLG zlib header (RFC 1950), with FCHECK chosen so the header
+// is a multiple of 31. Shared between `zlib` and `Compressor::zlib`.
+fn zlib_header() -> [u8; 2] {
+	let cmf: u8 = 0x78; // CM = 8 (deflate), CINFO = 7 (32K window)
+	let header_without_fcheck = (cmf as u16) * 256;
+	let remainder = header_without_fcheck % 31;
+	let flg = if remainder == 0 { 0 } else { 31 - remainder } as u8;
+	[cmf, flg]
+}
+
+// Wraps `deflate`'s output in a gzip container (RFC 1952): a 10-byte header,
+// the deflate stream, then an 8-byte trailer of CRC-32 and input length.
+pub fn gzip<T: Write>(file: &[u8], out: &mut T) {
+	out.write_all(&GZIP_HEADER).unwrap();
+	deflate(file, out);
+	out.write_all(&checksum::crc32(file).to_le_bytes()).unwrap();
+	out.write_all(&(file.len() as u32).to_le_bytes()).unwrap();
+}
+
+// Wraps `deflate`'s output in a zlib container (RFC 1950): a 2-byte
+// CMF/FLG header, the deflate stream, then a 4-byte big-endian Adler-32
+// trailer.
+pub fn zlib<T: Write>(file: &[u8], out: &mut T) {
+	out.write_all(&zlib_header()).unwrap();
+	deflate(file, out);
+	out.write_all(&checksum::adler32(file).to_be_bytes()).unwrap();
+}
+
+// how much unflushed data to accumulate before tokenizing a chunk
+const FLUSH_THRESHOLD: usize = 64 * 1024;
+
+// What, if anything, `Compressor` wraps its raw deflate stream in. Tracks
+// whatever running checksum the chosen container needs, computed
+// incrementally over bytes as they're written rather than requiring the
+// whole input to be buffered.
+enum Container {
+	Raw,
+	Gzip(checksum::Crc32, u64),
+	Zlib(checksum::Adler32),
+}
+
+// A `Write` adapter that compresses as it's fed, instead of requiring the
+// whole input up front like `deflate`. Bytes are tokenized in chunks once
+// enough lookahead has accumulated, and finished blocks are emitted through
+// a `DeflateWriter` as soon as they're ready. Call `finish` to flush the
+// final partial block.
+pub struct Compressor<T: Write> {
+	writer: DeflateWriter<T>,
+	matcher: lempel_ziv::Matcher,
+	// trailing WINDOW_SIZE bytes of history plus any bytes not yet tokenized
+	buffer: Vec<u8>,
+	// absolute stream position of buffer[0]
+	base: usize,
+	// absolute stream position up to which tokens have been written
+	flushed: usize,
+	container: Container,
 }
 
-struct DeflateWriter<'a, T: Write> {
-	out: &'a mut T,
+impl<T: Write> Compressor<T> {
+	pub fn new(out: T) -> Compressor<T> {
+		Compressor {
+			writer: DeflateWriter::new(out),
+			matcher: lempel_ziv::Matcher::new(),
+			buffer: Vec::new(),
+			base: 0,
+			flushed: 0,
+			container: Container::Raw,
+		}
+	}
+
+	// Same as `new`, but wraps the stream in a gzip container (RFC 1952):
+	// the 10-byte header is written immediately, and the CRC-32/length
+	// trailer once `finish` is called -- so data that doesn't fit in
+	// memory can still end up as a real gzip file, not just a bare deflate
+	// stream.
+	pub fn gzip(mut out: T) -> io::Result<Compressor<T>> {
+		out.write_all(&GZIP_HEADER)?;
+		Ok(Compressor {
+			container: Container::Gzip(checksum::Crc32::new(), 0),
+			..Compressor::new(out)
+		})
+	}
+
+	// Same as `new`, but wraps the stream in a zlib container (RFC 1950):
+	// the 2-byte CMF/FLG header is written immediately, and the
+	// big-endian Adler-32 trailer once `finish` is called.
+	pub fn zlib(mut out: T) -> io::Result<Compressor<T>> {
+		out.write_all(&zlib_header())?;
+		Ok(Compressor {
+			container: Container::Zlib(checksum::Adler32::new()),
+			..Compressor::new(out)
+		})
+	}
+
+	// Tokenizes and emits as much of the buffer as the matcher can safely
+	// decide (see `Matcher::advance`), once enough of it has piled up.
+	fn flush_ready(&mut self) -> io::Result<()> {
+		if self.base + self.buffer.len() - self.flushed < FLUSH_THRESHOLD {
+			return Ok(());
+		}
+
+		let start = self.flushed;
+		let new_tokens = self.matcher.advance(&self.buffer, self.base, false);
+		let history = &self.buffer[..start - self.base];
+		let blocks = block_splitter::block_split(history, &new_tokens);
+		write_blocks(&mut self.writer, &blocks, false)?;
+		self.flushed = self.matcher.position();
+
+		// keep only WINDOW_SIZE bytes of history behind the flushed point,
+		// since distances can't reach further back than that anyway
+		let keep_from = self.flushed.saturating_sub(WINDOW_SIZE);
+		if keep_from > self.base {
+			self.buffer.drain(..keep_from - self.base);
+			self.base = keep_from;
+		}
+		Ok(())
+	}
+
+	// Flushes the final partial block and the end-of-block symbol, then
+	// the container trailer (if any) once the deflate stream itself is done.
+	pub fn finish(mut self) -> io::Result<()> {
+		let start = self.flushed;
+		let tokens = self.matcher.advance(&self.buffer, self.base, true);
+		let history = &self.buffer[..start - self.base];
+		let blocks = block_splitter::block_split(history, &tokens);
+		write_blocks(&mut self.writer, &blocks, true)?;
+		let mut out = self.writer.finish()?;
+
+		match self.container {
+			Container::Raw => {}
+			Container::Gzip(crc, len) => {
+				out.write_all(&crc.finish().to_le_bytes())?;
+				out.write_all(&(len as u32).to_le_bytes())?;
+			}
+			Container::Zlib(adler) => out.write_all(&adler.finish().to_be_bytes())?,
+		}
+		Ok(())
+	}
+}
+
+impl<T: Write> Write for Compressor<T> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		match &mut self.container {
+			Container::Raw => {}
+			Container::Gzip(crc, len) => {
+				crc.update(buf);
+				*len += buf.len() as u64;
+			}
+			Container::Zlib(adler) => adler.update(buf),
+		}
+		self.buffer.extend_from_slice(buf);
+		self.flush_ready()?;
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+struct DeflateWriter<T: Write> {
+	out: T,
 	curr_bytes: u32,
 	curr_full_bits: u8,
 	literal_tree: huffman::Tree,
@@ -39,8 +224,8 @@ struct DeflateWriter<'a, T: Write> {
 	in_block: bool,
 }
 
-impl<'a, T: Write> DeflateWriter<'a, T> {
-	fn new(out: &'a mut T) -> DeflateWriter<'a, T> {
+impl<T: Write> DeflateWriter<T> {
+	fn new(out: T) -> DeflateWriter<T> {
 		DeflateWriter {
 			out,
 			curr_bytes: 0,
@@ -51,101 +236,239 @@ impl<'a, T: Write> DeflateWriter<'a, T> {
 		}
 	}
 
-	fn write_bits(&mut self, bits: u32, len: u8) {
+	fn write_bits(&mut self, bits: u32, len: u8) -> io::Result<()> {
 		// packs from LSB to MSB
 		// 16 bit max
 		self.curr_bytes |= bits << self.curr_full_bits;
 		self.curr_full_bits += len;
 		while self.curr_full_bits >= 8 {
-			self.out.write_all(&[(self.curr_bytes & 0xFF) as u8]).unwrap();
+			self.out.write_all(&[(self.curr_bytes & 0xFF) as u8])?;
 			self.curr_bytes >>= 8;
 			self.curr_full_bits -= 8;
 		}
+		Ok(())
 	}
 
-	fn write(&mut self, token: &Token) {
+	fn write(&mut self, token: &Token) -> io::Result<()> {
 		match token {
 			Token::Literal(value) => {
 				let huffman_code = self.literal_tree[*value as usize];
-				self.write_bits(huffman_code.code, huffman_code.length);
+				self.write_bits(huffman_code.code, huffman_code.length)?;
 			}
 			Token::Repeat(len, dist) => {
 				for (len_start, len_end, extra_bits, code) in &LEN_TO_CODE {
 					if len < len_end {
 						let huffman_code = self.literal_tree[*code as usize];
-						self.write_bits(huffman_code.code, huffman_code.length);
-						self.write_bits(len - len_start, *extra_bits);
+						self.write_bits(huffman_code.code, huffman_code.length)?;
+						self.write_bits(len - len_start, *extra_bits)?;
 						break;
 					}
 				}
 				for (dist_start, dist_end, extra_bits, code) in &DIST_TO_CODE {
 					if dist < dist_end {
 						let huffman_code = self.distance_tree[*code as usize];
-						self.write_bits(huffman_code.code, huffman_code.length);
-						self.write_bits(dist - dist_start, *extra_bits);
+						self.write_bits(huffman_code.code, huffman_code.length)?;
+						self.write_bits(dist - dist_start, *extra_bits)?;
 						break;
 					}
 				}
 			}
 		};
+		Ok(())
 	}
 
-	fn new_fixed_codes_block(&mut self, is_final: bool) {
+	fn new_fixed_codes_block(&mut self, is_final: bool) -> io::Result<()> {
 		if self.in_block {
 			// end of block
 			let huffman_code = self.literal_tree[256];
-			self.write_bits(huffman_code.code, huffman_code.length);
+			self.write_bits(huffman_code.code, huffman_code.length)?;
 		}
 		self.in_block = true;
-		self.write_bits(if is_final {1} else {0}, 1);
-		self.write_bits(1, 1);
-		self.write_bits(0, 1);
+		self.write_bits(if is_final {1} else {0}, 1)?;
+		self.write_bits(1, 1)?;
+		self.write_bits(0, 1)?;
 		self.literal_tree = huffman::calc_codes(&huffman::LITERAL_FIXED_CODES);
 		self.distance_tree = huffman::calc_codes(&huffman::DISTANCE_FIXED_CODES);
+		Ok(())
 	}
 
-	fn new_dynamic_codes_block(&mut self, is_final: bool, literal_code_lens: &[u8], distance_code_lens: &[u8]) {
+	fn new_dynamic_codes_block(&mut self, is_final: bool, literal_code_lens: &[u8], distance_code_lens: &[u8]) -> io::Result<()> {
 		if self.in_block {
 			// end of block
 			let huffman_code = self.literal_tree[256];
-			self.write_bits(huffman_code.code, huffman_code.length);
+			self.write_bits(huffman_code.code, huffman_code.length)?;
 		}
 		self.in_block = true;
-		self.write_bits(if is_final {1} else {0}, 1);
-		self.write_bits(0, 1);
-		self.write_bits(1, 1);
-		// encode tree
-		self.write_bits(286 - 257, 5); // HLIT
-		self.write_bits(30 - 1, 5); // HDIST
-		self.write_bits(19 - 4, 4); // HCLEN
-		let code_len_of_code_order: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
-		let code_len_of_code: [u8; 19] = [4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 0, 0, 0];
-		let code_len_tree = huffman::calc_codes(&code_len_of_code);
-		for i in 0..19 { // code lengths for the code length alphabet
-			self.write_bits(code_len_of_code[code_len_of_code_order[i]] as u32, 3);
-		}
-		for i in 0..286 { // code lengths for the literal/length alphabet
-			let huffman_code = code_len_tree[literal_code_lens[i] as usize];
-			self.write_bits(huffman_code.code, huffman_code.length);
-		}
-		for i in 0..30 { // code lengths for the distance alphabet
-			let huffman_code = code_len_tree[distance_code_lens[i] as usize];
-			self.write_bits(huffman_code.code, huffman_code.length);
+		self.write_bits(if is_final {1} else {0}, 1)?;
+		self.write_bits(0, 1)?;
+		self.write_bits(1, 1)?;
+
+		let header = build_dynamic_header(literal_code_lens, distance_code_lens);
+		let code_len_tree = huffman::calc_codes(&header.code_len_lens);
+
+		self.write_bits(header.hlit as u32 - 257, 5)?; // HLIT
+		self.write_bits(header.hdist as u32 - 1, 5)?; // HDIST
+		self.write_bits(header.hclen as u32 - 4, 4)?; // HCLEN
+		for &index in &CODE_LEN_ORDER[..header.hclen] { // code lengths for the code length alphabet
+			self.write_bits(header.code_len_lens[index] as u32, 3)?;
 		}
+		for &(symbol, extra_value, extra_bits) in &header.rle { // RLE-compressed code lengths for lit/length + distance
+			let huffman_code = code_len_tree[symbol as usize];
+			self.write_bits(huffman_code.code, huffman_code.length)?;
+			self.write_bits(extra_value, extra_bits)?;
+		}
+
 		self.literal_tree = huffman::calc_codes(literal_code_lens);
 		self.distance_tree = huffman::calc_codes(distance_code_lens);
+		Ok(())
+	}
+
+	fn new_stored_block(&mut self, is_final: bool, data: &[u8]) -> io::Result<()> {
+		if self.in_block {
+			// end of block
+			let huffman_code = self.literal_tree[256];
+			self.write_bits(huffman_code.code, huffman_code.length)?;
+		}
+		self.in_block = false; // stored blocks are self-terminating, no EOB symbol follows
+		self.write_bits(if is_final {1} else {0}, 1)?;
+		self.write_bits(0, 1)?;
+		self.write_bits(0, 1)?;
+		// align to a byte boundary before LEN/NLEN
+		if self.curr_full_bits > 0 {
+			self.out.write_all(&[(self.curr_bytes & 0xFF) as u8])?;
+			self.curr_bytes = 0;
+			self.curr_full_bits = 0;
+		}
+		let len = data.len() as u16;
+		self.out.write_all(&len.to_le_bytes())?;
+		self.out.write_all(&(!len).to_le_bytes())?;
+		self.out.write_all(data)?;
+		Ok(())
 	}
-}
 
-impl<'a, T: Write> Drop for DeflateWriter<'a, T> {
-	fn drop(&mut self) {
-		// end of block
-		let huffman_code = self.literal_tree[256];
-		self.write_bits(huffman_code.code, huffman_code.length);
+	// Writes the trailing end-of-block symbol (if a Huffman block is still
+	// open) and flushes any partial final byte, then hands back the
+	// underlying writer so a container trailer can still be appended to it.
+	// Must be called exactly once, after the last block has been written.
+	fn finish(mut self) -> io::Result<T> {
+		if self.in_block {
+			let huffman_code = self.literal_tree[256];
+			self.write_bits(huffman_code.code, huffman_code.length)?;
+		}
 		if self.curr_full_bits > 0 {
-			self.out.write_all(&[(self.curr_bytes & 0xFF) as u8]).unwrap();
+			self.out.write_all(&[(self.curr_bytes & 0xFF) as u8])?;
 		}
+		Ok(self.out)
+	}
+}
+
+fn trimmed_len(code_lens: &[u8], min: usize) -> usize {
+	let mut len = code_lens.len();
+	while len > min && code_lens[len - 1] == 0 {
+		len -= 1;
+	}
+	len
+}
+
+// Order the code-length alphabet's lengths are written in (RFC 1951 3.2.7).
+const CODE_LEN_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// Everything a dynamic block's header needs: the trimmed HLIT/HDIST symbol
+// counts, the RLE-compressed lit/length + distance code lengths, and the
+// code-length alphabet's own (Huffman-coded) lengths with its HCLEN count.
+// Shared between `new_dynamic_codes_block` (which writes it) and
+// `block_splitter` (which needs its exact bit cost to compare candidates).
+pub(super) struct DynamicHeader {
+	pub hlit: usize,
+	pub hdist: usize,
+	pub rle: Vec<(u8, u32, u8)>,
+	pub code_len_lens: Vec<u8>,
+	pub hclen: usize,
+}
+
+pub(super) fn build_dynamic_header(literal_code_lens: &[u8], distance_code_lens: &[u8]) -> DynamicHeader {
+	// trim trailing zero lengths; HLIT/HDIST still cover at least the
+	// mandatory minimum of symbols
+	let hlit = trimmed_len(literal_code_lens, 257);
+	let hdist = trimmed_len(distance_code_lens, 1);
+
+	let combined: Vec<u8> = literal_code_lens[..hlit].iter()
+		.chain(distance_code_lens[..hdist].iter())
+		.cloned()
+		.collect();
+	let rle = rle_encode_lengths(&combined);
+
+	let mut code_len_freqs = [0u32; 19];
+	for &(symbol, _, _) in &rle {
+		code_len_freqs[symbol as usize] += 1;
+	}
+	let code_len_lens = huffman::build_code_lengths(&code_len_freqs);
+
+	let mut hclen = 19;
+	while hclen > 4 && code_len_lens[CODE_LEN_ORDER[hclen - 1]] == 0 {
+		hclen -= 1;
+	}
+
+	DynamicHeader { hlit, hdist, rle, code_len_lens, hclen }
+}
+
+// Total bits a dynamic block's header (HLIT/HDIST/HCLEN fields, the
+// code-length alphabet's own lengths, and the RLE-compressed code lengths
+// themselves) takes to write.
+pub(super) fn dynamic_header_bits(header: &DynamicHeader) -> u64 {
+	let mut bits = 5 + 5 + 4 + 3 * header.hclen as u64;
+	for &(symbol, _, extra_bits) in &header.rle {
+		bits += header.code_len_lens[symbol as usize] as u64 + extra_bits as u64;
+	}
+	bits
+}
+
+// Encodes a sequence of code lengths with the RFC 1951 3.2.7 code-length
+// alphabet: symbol 16 copies the previous length 3-6 times (2 extra bits,
+// value-3), 17 repeats a zero length 3-10 times (3 extra bits, value-3), 18
+// repeats a zero length 11-138 times (7 extra bits, value-11).
+fn rle_encode_lengths(code_lens: &[u8]) -> Vec<(u8, u32, u8)> {
+	let mut out = Vec::new();
+	let mut i = 0;
+	while i < code_lens.len() {
+		let value = code_lens[i];
+		let mut run = 1;
+		while i + run < code_lens.len() && code_lens[i + run] == value {
+			run += 1;
+		}
+
+		if value == 0 {
+			let mut remaining = run;
+			while remaining >= 3 {
+				if remaining >= 11 {
+					let chunk = remaining.min(138);
+					out.push((18, chunk as u32 - 11, 7));
+					remaining -= chunk;
+				} else {
+					let chunk = remaining.min(10);
+					out.push((17, chunk as u32 - 3, 3));
+					remaining -= chunk;
+				}
+			}
+			for _ in 0..remaining {
+				out.push((0, 0, 0));
+			}
+		} else {
+			out.push((value, 0, 0));
+			let mut remaining = run - 1;
+			while remaining >= 3 {
+				let chunk = remaining.min(6);
+				out.push((16, chunk as u32 - 3, 2));
+				remaining -= chunk;
+			}
+			for _ in 0..remaining {
+				out.push((value, 0, 0));
+			}
+		}
+
+		i += run;
 	}
+	out
 }
 
 const LEN_TO_CODE: [(u32, u32, u8, u32); 29] = [
@@ -0,0 +1,295 @@
+use crate::huffman;
+use super::{Token, LEN_TO_CODE, DIST_TO_CODE};
+
+const MAX_CODE_LEN: u8 = 15; // RFC 1951 3.2.2
+const MAX_STORED_LEN: usize = 65535; // stored block LEN field is 16 bits
+// granularity of the greedy block-splitting scan, in tokens
+const SPLIT_CHUNK: usize = 4096;
+
+pub enum Block {
+	FixedCodes { tokens: Vec<Token> },
+	DynamicCodes { tokens: Vec<Token>, literal_code_lens: Vec<u8>, distance_code_lens: Vec<u8> },
+	Stored { data: Vec<u8> },
+}
+
+enum BlockKind {
+	Fixed,
+	Dynamic,
+	Stored,
+}
+
+// The cheapest way found to encode a run of tokens as one block, and its
+// estimated bit cost (used both to pick among fixed/dynamic/stored and to
+// decide whether splitting a run into two blocks pays for itself).
+struct Evaluation {
+	kind: BlockKind,
+	bits: u64,
+	literal_code_lens: Vec<u8>,
+	distance_code_lens: Vec<u8>,
+}
+
+fn evaluate(tokens: &[Token]) -> Evaluation {
+	let (literal_freqs, distance_freqs) = count_freqs(tokens);
+	evaluate_freqs(&literal_freqs, &distance_freqs, token_byte_len(tokens))
+}
+
+// Same cost/kind evaluation as `evaluate`, but from already-counted
+// histograms instead of re-counting `tokens` from scratch. The Huffman
+// builds and header cost below only depend on the (fixed-size, 286 + 30
+// symbol) histograms, not on how many tokens fed them, so this is cheap
+// regardless of how much of the stream `literal_freqs`/`distance_freqs`
+// summarize -- which is what lets `block_split`'s growth scan stay linear
+// instead of re-scanning the whole accumulated run at every step.
+fn evaluate_freqs(literal_freqs: &[u32], distance_freqs: &[u32], byte_len: usize) -> Evaluation {
+	// symbol 256 (end-of-block) always needs a code, even if this count
+	// came from summing per-chunk histograms that never charged it
+	let mut literal_freqs = literal_freqs.to_vec();
+	literal_freqs[256] += 1;
+
+	let literal_code_lens = huffman::build_length_limited_code_lengths(&literal_freqs, MAX_CODE_LEN);
+	let distance_code_lens = huffman::build_length_limited_code_lengths(distance_freqs, MAX_CODE_LEN);
+
+	let header = super::build_dynamic_header(&literal_code_lens, &distance_code_lens);
+	let dynamic_bits = symbol_bits(&literal_freqs, &literal_code_lens)
+		+ symbol_bits(distance_freqs, &distance_code_lens)
+		+ super::dynamic_header_bits(&header);
+
+	let fixed_bits = symbol_bits(&literal_freqs, &huffman::LITERAL_FIXED_CODES[..literal_freqs.len()])
+		+ symbol_bits(distance_freqs, &huffman::DISTANCE_FIXED_CODES);
+
+	let stored_bits = stored_cost_bits(byte_len);
+
+	let (bits, kind) = if dynamic_bits <= fixed_bits && dynamic_bits <= stored_bits {
+		(dynamic_bits, BlockKind::Dynamic)
+	} else if fixed_bits <= stored_bits {
+		(fixed_bits, BlockKind::Fixed)
+	} else {
+		(stored_bits, BlockKind::Stored)
+	};
+
+	Evaluation { kind, bits, literal_code_lens, distance_code_lens }
+}
+
+fn add_freqs(a: &[u32], b: &[u32]) -> Vec<u32> {
+	a.iter().zip(b).map(|(x, y)| x + y).collect()
+}
+
+// A stored block's LEN field is 16 bits, so data longer than MAX_STORED_LEN
+// needs multiple stored blocks, each paying its own 5-byte (BTYPE + LEN +
+// NLEN) header.
+fn stored_cost_bits(byte_len: usize) -> u64 {
+	if byte_len == 0 {
+		return 40;
+	}
+	let num_blocks = byte_len.div_ceil(MAX_STORED_LEN) as u64;
+	8 * byte_len as u64 + 40 * num_blocks
+}
+
+fn token_byte_len(tokens: &[Token]) -> usize {
+	tokens.iter().map(|token| match token {
+		Token::Literal(_) => 1,
+		Token::Repeat(len, _) => *len as usize,
+	}).sum()
+}
+
+// Splits a run of tokens into DEFLATE blocks. `history` is whatever bytes
+// precede these tokens in the overall stream (empty for a one-shot whole-file
+// compression); back-references within `tokens` may reach into it.
+//
+// Each block picks whichever of fixed, dynamic, or stored encoding is
+// cheapest for its own token/symbol distribution (`evaluate`). On top of
+// that, a greedy scan grows each block chunk by chunk for as long as merging
+// the next chunk in stays cheaper than closing the block and paying for a
+// second header -- so runs whose symbol distribution drifts partway through
+// end up as separate blocks instead of one block compromised across both.
+pub fn block_split(history: &[u8], tokens: &[Token]) -> Vec<Block> {
+	if tokens.is_empty() {
+		return build_block(&evaluate(tokens), tokens, &[]);
+	}
+
+	let data = reconstruct(history, tokens);
+	let byte_offsets = token_byte_offsets(tokens);
+
+	let mut blocks = Vec::new();
+	let mut start = 0;
+	while start < tokens.len() {
+		let mut end = (start + SPLIT_CHUNK).min(tokens.len());
+		// running histograms for tokens[start..end], grown one chunk at a
+		// time below instead of re-counted from scratch on every step
+		let (mut literal_freqs, mut distance_freqs) = count_freqs(&tokens[start..end]);
+		let mut byte_len = byte_offsets[end] - byte_offsets[start];
+		let mut current_bits = evaluate_freqs(&literal_freqs, &distance_freqs, byte_len).bits;
+
+		while end < tokens.len() {
+			let next_end = (end + SPLIT_CHUNK).min(tokens.len());
+			let (chunk_literal_freqs, chunk_distance_freqs) = count_freqs(&tokens[end..next_end]);
+			let chunk_byte_len = byte_offsets[next_end] - byte_offsets[end];
+			let chunk_bits = evaluate_freqs(&chunk_literal_freqs, &chunk_distance_freqs, chunk_byte_len).bits;
+
+			let merged_literal_freqs = add_freqs(&literal_freqs, &chunk_literal_freqs);
+			let merged_distance_freqs = add_freqs(&distance_freqs, &chunk_distance_freqs);
+			let merged_byte_len = byte_len + chunk_byte_len;
+			let merged_bits = evaluate_freqs(&merged_literal_freqs, &merged_distance_freqs, merged_byte_len).bits;
+
+			if merged_bits > current_bits + chunk_bits {
+				break;
+			}
+
+			literal_freqs = merged_literal_freqs;
+			distance_freqs = merged_distance_freqs;
+			byte_len = merged_byte_len;
+			current_bits = merged_bits;
+			end = next_end;
+		}
+
+		let block_tokens = &tokens[start..end];
+		let block_data = &data[byte_offsets[start]..byte_offsets[end]];
+		blocks.extend(build_block(&evaluate(block_tokens), block_tokens, block_data));
+		start = end;
+	}
+	blocks
+}
+
+fn build_block(evaluation: &Evaluation, tokens: &[Token], data: &[u8]) -> Vec<Block> {
+	match evaluation.kind {
+		BlockKind::Fixed => vec![Block::FixedCodes { tokens: tokens.to_vec() }],
+		BlockKind::Dynamic => vec![Block::DynamicCodes {
+			tokens: tokens.to_vec(),
+			literal_code_lens: evaluation.literal_code_lens.clone(),
+			distance_code_lens: evaluation.distance_code_lens.clone(),
+		}],
+		BlockKind::Stored if data.is_empty() => vec![Block::Stored { data: Vec::new() }],
+		BlockKind::Stored => data.chunks(MAX_STORED_LEN)
+			.map(|chunk| Block::Stored { data: chunk.to_vec() })
+			.collect(),
+	}
+}
+
+fn symbol_bits(freqs: &[u32], code_lens: &[u8]) -> u64 {
+	freqs.iter().zip(code_lens).map(|(&freq, &len)| freq as u64 * len as u64).sum()
+}
+
+// Cumulative byte length covered by tokens[..i], for every i from 0 to
+// tokens.len(): token_byte_offsets(tokens)[i] is where tokens[i] starts.
+fn token_byte_offsets(tokens: &[Token]) -> Vec<usize> {
+	let mut offsets = Vec::with_capacity(tokens.len() + 1);
+	let mut pos = 0;
+	offsets.push(pos);
+	for token in tokens {
+		pos += match token {
+			Token::Literal(_) => 1,
+			Token::Repeat(len, _) => *len as usize,
+		};
+		offsets.push(pos);
+	}
+	offsets
+}
+
+// Replays the token stream (literals and back-references, which may reach
+// into `history`) to recover the bytes `tokens` encodes, for use when a
+// block is cheaper stored than compressed.
+fn reconstruct(history: &[u8], tokens: &[Token]) -> Vec<u8> {
+	let mut data = history.to_vec();
+	let history_len = data.len();
+	for token in tokens {
+		match token {
+			Token::Literal(value) => data.push(*value),
+			Token::Repeat(len, dist) => {
+				let start = data.len() - *dist as usize;
+				for i in 0..*len as usize {
+					data.push(data[start + i]);
+				}
+			}
+		}
+	}
+	data.split_off(history_len)
+}
+
+// Raw per-symbol counts over `tokens`: 286 literal/length symbols (0-285)
+// and 30 distance symbols. Does not account for the mandatory end-of-block
+// occurrence of symbol 256 -- callers that build Huffman codes from these
+// (see `evaluate_freqs`) add that in themselves, so that counts from
+// disjoint token runs can be summed without double-counting it.
+fn count_freqs(tokens: &[Token]) -> (Vec<u32>, Vec<u32>) {
+	let mut literal_freqs = vec![0u32; 286];
+	let mut distance_freqs = vec![0u32; 30];
+
+	for token in tokens {
+		match token {
+			Token::Literal(value) => literal_freqs[*value as usize] += 1,
+			Token::Repeat(len, dist) => {
+				for &(_, len_end, _, code) in &LEN_TO_CODE {
+					if *len < len_end {
+						literal_freqs[code as usize] += 1;
+						break;
+					}
+				}
+				for &(_, dist_end, _, code) in &DIST_TO_CODE {
+					if *dist < dist_end {
+						distance_freqs[code as usize] += 1;
+						break;
+					}
+				}
+			}
+		}
+	}
+
+	(literal_freqs, distance_freqs)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn lcg(seed: &mut u64) -> u64 {
+		*seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+		*seed
+	}
+
+	// Blocks can encode a run either as tokens (Fixed/Dynamic) or as the
+	// equivalent raw bytes (Stored), so the only invariant that holds across
+	// both is total byte length, not token count.
+	fn byte_len(blocks: &[Block]) -> usize {
+		blocks.iter().map(|block| match block {
+			Block::FixedCodes { tokens } => token_byte_len(tokens),
+			Block::DynamicCodes { tokens, .. } => token_byte_len(tokens),
+			Block::Stored { data } => data.len(),
+		}).sum()
+	}
+
+	#[test]
+	fn block_split_never_drops_or_empties_on_empty_input() {
+		let blocks = block_split(&[], &[]);
+		assert_eq!(blocks.len(), 1, "empty input must still produce exactly one block");
+	}
+
+	#[test]
+	fn block_split_preserves_every_byte_across_a_run_split() {
+		// a distribution that shifts partway through, to push the greedy
+		// scan towards actually opening a second block
+		let mut seed = 3u64;
+		let mut tokens: Vec<Token> = (0..10_000).map(|_| Token::Literal(1)).collect();
+		tokens.extend((0..10_000).map(|_| Token::Literal((lcg(&mut seed) >> 33) as u8)));
+
+		let blocks = block_split(&[], &tokens);
+		assert_eq!(byte_len(&blocks), token_byte_len(&tokens));
+	}
+
+	#[test]
+	fn reconstruct_replays_literals_and_back_references() {
+		let tokens = vec![
+			Token::Literal(b'a'),
+			Token::Literal(b'b'),
+			Token::Literal(b'c'),
+			Token::Repeat(3, 3), // copies "abc" again
+		];
+		assert_eq!(reconstruct(&[], &tokens), b"abcabc");
+	}
+
+	#[test]
+	fn reconstruct_can_reach_into_history() {
+		let history = b"prefix-".to_vec();
+		let tokens = vec![Token::Repeat(6, 7)]; // copies "prefix" from history
+		assert_eq!(reconstruct(&history, &tokens), b"prefix");
+	}
+}
@@ -0,0 +1,218 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::Token;
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+pub const WINDOW_SIZE: usize = 32 * 1024;
+
+type Key = [u8; MIN_MATCH];
+type Chains = HashMap<Key, VecDeque<usize>>;
+
+// Greedy LZ77 match finder over the whole input, using a hash chain keyed on
+// the first MIN_MATCH bytes to find candidate back-references.
+pub fn lempel_ziv(data: &[u8]) -> Vec<Token> {
+	Matcher::new().advance(data, 0, true)
+}
+
+// Incremental version of the same match finder, for tokenizing a stream as
+// it arrives instead of all at once. Bytes are addressed by absolute
+// position (`data[i - base]` is byte `i`), so callers can slide `data`
+// forward (dropping bytes the window can no longer reach) without losing
+// track of where they are.
+pub struct Matcher {
+	chains: Chains,
+	// positions not yet evicted from `chains`, oldest first, paired with the
+	// hash key they were inserted under so eviction can find their chain
+	order: VecDeque<(usize, Key)>,
+	// absolute position of the next byte to decide a token for
+	pos: usize,
+}
+
+impl Matcher {
+	pub fn new() -> Matcher {
+		Matcher { chains: HashMap::new(), order: VecDeque::new(), pos: 0 }
+	}
+
+	// Tokenizes forward from wherever the last call left off, as far as it
+	// safely can: a match's length can't be known for certain until there
+	// are MAX_MATCH bytes of lookahead past it (otherwise more data might
+	// extend it), so positions too close to the end of `data` are left for
+	// a later call. `final_input` lifts that restriction, for the last call
+	// once no more bytes are ever coming.
+	pub fn advance(&mut self, data: &[u8], base: usize, final_input: bool) -> Vec<Token> {
+		let end = base + data.len();
+		let mut tokens = Vec::new();
+
+		while self.pos < end {
+			if !final_input && self.pos + MAX_MATCH > end {
+				break;
+			}
+
+			let (match_len, match_dist) = self.find_match(data, base, self.pos);
+			if match_len >= MIN_MATCH {
+				tokens.push(Token::Repeat(match_len as u32, match_dist as u32));
+				for i in self.pos..self.pos + match_len {
+					self.insert_hash(data, base, i);
+				}
+				self.pos += match_len;
+			} else {
+				tokens.push(Token::Literal(data[self.pos - base]));
+				self.insert_hash(data, base, self.pos);
+				self.pos += 1;
+			}
+		}
+
+		tokens
+	}
+
+	// Absolute position up to which tokens have been decided so far.
+	pub fn position(&self) -> usize {
+		self.pos
+	}
+
+	fn insert_hash(&mut self, data: &[u8], base: usize, pos: usize) {
+		if pos + MIN_MATCH > base + data.len() {
+			return;
+		}
+		let key = [data[pos - base], data[pos - base + 1], data[pos - base + 2]];
+		self.chains.entry(key).or_default().push_back(pos);
+		self.order.push_back((pos, key));
+		self.evict(pos);
+	}
+
+	// Drops chain entries that have fallen behind the match window, so
+	// `chains` stays bounded by WINDOW_SIZE regardless of total stream
+	// length -- distances can never reach further back than that anyway, so
+	// a match finder that kept every position forever would just be holding
+	// onto candidates it can never use.
+	fn evict(&mut self, pos: usize) {
+		let window_start = pos.saturating_sub(WINDOW_SIZE);
+		while let Some(&(oldest, key)) = self.order.front() {
+			if oldest >= window_start {
+				break;
+			}
+			self.order.pop_front();
+			if let Some(positions) = self.chains.get_mut(&key) {
+				positions.pop_front();
+				if positions.is_empty() {
+					self.chains.remove(&key);
+				}
+			}
+		}
+	}
+
+	fn find_match(&self, data: &[u8], base: usize, pos: usize) -> (usize, usize) {
+		if pos + MIN_MATCH > base + data.len() {
+			return (0, 0);
+		}
+		let key = [data[pos - base], data[pos - base + 1], data[pos - base + 2]];
+		let Some(candidates) = self.chains.get(&key) else {
+			return (0, 0);
+		};
+
+		// candidates below `base` are no longer present in `data` at all
+		let window_start = pos.saturating_sub(WINDOW_SIZE).max(base);
+		let max_len = MAX_MATCH.min(base + data.len() - pos);
+
+		let mut best_len = 0;
+		let mut best_dist = 0;
+		for &cand in candidates.iter().rev() {
+			if cand < window_start {
+				break;
+			}
+			let len = match_length(data, base, cand, pos, max_len);
+			if len > best_len {
+				best_len = len;
+				best_dist = pos - cand;
+				if best_len == max_len {
+					break;
+				}
+			}
+		}
+
+		(best_len, best_dist)
+	}
+}
+
+fn match_length(data: &[u8], base: usize, a: usize, b: usize, max_len: usize) -> usize {
+	let mut len = 0;
+	while len < max_len && data[a - base + len] == data[b - base + len] {
+		len += 1;
+	}
+	len
+}
+
+// Replays literal/repeat tokens into the bytes they encode, for checking a
+// tokenization round-trips without needing a full DEFLATE bitstream decoder.
+#[cfg(test)]
+fn replay(tokens: &[Token]) -> Vec<u8> {
+	let mut out = Vec::new();
+	for token in tokens {
+		match token {
+			Token::Literal(value) => out.push(*value),
+			Token::Repeat(len, dist) => {
+				let start = out.len() - *dist as usize;
+				for i in 0..*len as usize {
+					out.push(out[start + i]);
+				}
+			}
+		}
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn lcg(seed: &mut u64) -> u64 {
+		*seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+		*seed
+	}
+
+	#[test]
+	fn one_shot_tokenization_round_trips() {
+		let mut seed = 1u64;
+		let data: Vec<u8> = (0..50_000).map(|_| (lcg(&mut seed) >> 33) as u8 % 8).collect();
+		assert_eq!(replay(&lempel_ziv(&data)), data);
+	}
+
+	// Regression test for a bug where tokenizing a sliding/trimmed buffer
+	// from scratch on every call could re-decide an already-finalized
+	// token differently, corrupting the stream. `Matcher` must tokenize
+	// every position exactly once, so feeding the same data incrementally
+	// (in small, window-straddling chunks) has to decode to the exact same
+	// bytes as tokenizing it all at once. The tokens themselves may differ:
+	// positions within MIN_MATCH - 1 bytes of the buffer's end when a match
+	// ends there are never hashed, so a later match can pick another distance.
+	#[test]
+	fn incremental_tokenization_matches_one_shot_across_chunk_sizes() {
+		let mut seed = 2u64;
+		let mut data = vec![b'x'; 40_000];
+		data.extend((0..40_000).map(|_| (lcg(&mut seed) >> 33) as u8));
+		data.extend(vec![b'y'; 40_000]);
+
+		let one_shot = replay(&lempel_ziv(&data));
+		assert_eq!(one_shot, data);
+
+		for &chunk_size in &[1usize, 7, 500, 4096, 70_000] {
+			let mut matcher = Matcher::new();
+			let mut tokens = Vec::new();
+			let mut base = 0;
+			let mut buffer: Vec<u8> = Vec::new();
+			for chunk in data.chunks(chunk_size) {
+				buffer.extend_from_slice(chunk);
+				tokens.extend(matcher.advance(&buffer, base, false));
+				let keep_from = matcher.position().saturating_sub(WINDOW_SIZE);
+				if keep_from > base {
+					buffer.drain(..keep_from - base);
+					base = keep_from;
+				}
+			}
+			tokens.extend(matcher.advance(&buffer, base, true));
+
+			assert_eq!(replay(&tokens), data, "chunk size {chunk_size} corrupted the stream");
+		}
+	}
+}
@@ -0,0 +1,238 @@
+use std::collections::BinaryHeap;
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy, Default)]
+pub struct HuffmanCode {
+	pub code: u32,
+	pub length: u8,
+}
+
+pub type Tree = Vec<HuffmanCode>;
+
+// RFC 1951 3.2.6
+pub const LITERAL_FIXED_CODES: [u8; 288] = {
+	let mut lens = [8u8; 288];
+	let mut i = 144;
+	while i < 256 {
+		lens[i] = 9;
+		i += 1;
+	}
+	let mut i = 256;
+	while i < 280 {
+		lens[i] = 7;
+		i += 1;
+	}
+	lens
+};
+
+pub const DISTANCE_FIXED_CODES: [u8; 30] = [5; 30];
+
+// RFC 1951 3.2.2: canonical Huffman codes from per-symbol lengths.
+pub fn calc_codes(code_lengths: &[u8]) -> Tree {
+	let max_bits = code_lengths.iter().cloned().max().unwrap_or(0) as usize;
+	let mut bl_count = vec![0u32; max_bits + 1];
+	for &len in code_lengths {
+		if len > 0 {
+			bl_count[len as usize] += 1;
+		}
+	}
+	let mut next_code = vec![0u32; max_bits + 1];
+	let mut code = 0u32;
+	for bits in 1..=max_bits {
+		code = (code + bl_count[bits - 1]) << 1;
+		next_code[bits] = code;
+	}
+	code_lengths.iter().map(|&length| {
+		if length == 0 {
+			HuffmanCode { code: 0, length: 0 }
+		} else {
+			let assigned = next_code[length as usize];
+			next_code[length as usize] += 1;
+			HuffmanCode { code: reverse_bits(assigned, length), length }
+		}
+	}).collect()
+}
+
+// write_bits packs LSB-first, but RFC 1951 codes are conventionally built MSB-first.
+fn reverse_bits(code: u32, length: u8) -> u32 {
+	let mut code = code;
+	let mut reversed = 0u32;
+	for _ in 0..length {
+		reversed = (reversed << 1) | (code & 1);
+		code >>= 1;
+	}
+	reversed
+}
+
+struct HuffNode {
+	freq: u32,
+	// ties broken by insertion order so the result is deterministic
+	seq: u32,
+	depth_of: Vec<(usize, u8)>, // leaf symbol index -> depth, for leaves; for internal nodes, merged children
+}
+
+impl Eq for HuffNode {}
+impl PartialEq for HuffNode {
+	fn eq(&self, other: &Self) -> bool {
+		self.freq == other.freq && self.seq == other.seq
+	}
+}
+impl Ord for HuffNode {
+	fn cmp(&self, other: &Self) -> Ordering {
+		// BinaryHeap is a max-heap; we want the smallest freq first
+		other.freq.cmp(&self.freq).then_with(|| other.seq.cmp(&self.seq))
+	}
+}
+impl PartialOrd for HuffNode {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+// Builds per-symbol code lengths from frequencies via the classic Huffman
+// tree algorithm. Does not enforce the DEFLATE 15-bit limit.
+pub fn build_code_lengths(freqs: &[u32]) -> Vec<u8> {
+	let used: Vec<usize> = (0..freqs.len()).filter(|&i| freqs[i] > 0).collect();
+	let mut lengths = vec![0u8; freqs.len()];
+	if used.is_empty() {
+		return lengths;
+	}
+	if used.len() == 1 {
+		lengths[used[0]] = 1;
+		return lengths;
+	}
+
+	let mut heap: BinaryHeap<HuffNode> = BinaryHeap::new();
+	let mut seq = 0u32;
+	for &i in &used {
+		heap.push(HuffNode { freq: freqs[i], seq, depth_of: vec![(i, 0)] });
+		seq += 1;
+	}
+
+	while heap.len() > 1 {
+		let a = heap.pop().unwrap();
+		let b = heap.pop().unwrap();
+		let mut depth_of = Vec::with_capacity(a.depth_of.len() + b.depth_of.len());
+		depth_of.extend(a.depth_of.into_iter().map(|(i, d)| (i, d + 1)));
+		depth_of.extend(b.depth_of.into_iter().map(|(i, d)| (i, d + 1)));
+		heap.push(HuffNode { freq: a.freq + b.freq, seq, depth_of });
+		seq += 1;
+	}
+
+	for (i, depth) in heap.pop().unwrap().depth_of {
+		lengths[i] = depth;
+	}
+	lengths
+}
+
+#[derive(Clone)]
+struct PackageNode {
+	weight: u64,
+	// original symbol indices contained in this node, one entry per leaf it packages
+	symbols: Vec<usize>,
+}
+
+// Package-merge (coin-collector) construction of code lengths that never
+// exceed `limit` bits, per Larmore & Hirschberg. `build_code_lengths` alone
+// can't guarantee this: a sufficiently skewed frequency distribution
+// produces depths beyond what DEFLATE's 15-bit code lengths allow.
+pub fn build_length_limited_code_lengths(freqs: &[u32], limit: u8) -> Vec<u8> {
+	let used: Vec<usize> = (0..freqs.len()).filter(|&i| freqs[i] > 0).collect();
+	let mut lengths = vec![0u8; freqs.len()];
+	if used.is_empty() {
+		return lengths;
+	}
+	if used.len() == 1 {
+		lengths[used[0]] = 1;
+		return lengths;
+	}
+
+	let mut originals: Vec<PackageNode> = used.iter()
+		.map(|&i| PackageNode { weight: freqs[i] as u64, symbols: vec![i] })
+		.collect();
+	originals.sort_by_key(|node| node.weight);
+
+	let mut level = originals.clone();
+	for _ in 0..limit.saturating_sub(1) {
+		let packaged = package_pairs(&level);
+		let mut merged: Vec<PackageNode> = packaged.into_iter().chain(originals.iter().cloned()).collect();
+		merged.sort_by_key(|node| node.weight);
+		level = merged;
+	}
+
+	let take = 2 * used.len() - 2;
+	for node in level.into_iter().take(take) {
+		for symbol in node.symbols {
+			lengths[symbol] += 1;
+		}
+	}
+	lengths
+}
+
+// Combines adjacent pairs of a weight-sorted list into packages; an odd
+// trailing item can't be paired and is dropped for this pass.
+fn package_pairs(level: &[PackageNode]) -> Vec<PackageNode> {
+	level.chunks_exact(2)
+		.map(|pair| PackageNode {
+			weight: pair[0].weight + pair[1].weight,
+			symbols: pair[0].symbols.iter().chain(pair[1].symbols.iter()).cloned().collect(),
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// A complete canonical Huffman code must satisfy the Kraft equality
+	// (not just the inequality): sum(2^-length) == 1 over used symbols.
+	fn kraft_sum(lengths: &[u8]) -> f64 {
+		lengths.iter().filter(|&&l| l > 0).map(|&l| 2f64.powi(-(l as i32))).sum()
+	}
+
+	#[test]
+	fn build_code_lengths_produces_a_complete_code() {
+		let freqs = [5u32, 1, 1, 2, 3, 0, 0, 8];
+		let lengths = build_code_lengths(&freqs);
+		assert!((kraft_sum(&lengths) - 1.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn calc_codes_assigns_distinct_codes_to_used_symbols() {
+		let lengths = build_code_lengths(&[5u32, 1, 1, 2, 3, 0, 0, 8]);
+		let tree = calc_codes(&lengths);
+		let mut seen = std::collections::HashSet::new();
+		for (i, code) in tree.iter().enumerate() {
+			if lengths[i] > 0 {
+				assert!(seen.insert((code.code, code.length)), "duplicate code for symbol {i}");
+			}
+		}
+	}
+
+	// A Fibonacci-weighted frequency distribution is a standard stress case
+	// for Huffman construction: plain `build_code_lengths` happily produces
+	// depths well past DEFLATE's 15-bit code length limit, which is exactly
+	// what `build_length_limited_code_lengths` exists to prevent.
+	#[test]
+	fn build_length_limited_code_lengths_respects_the_limit_on_skewed_frequencies() {
+		let mut fib = vec![1u32, 1];
+		while fib.len() < 32 {
+			let next = fib[fib.len() - 1] + fib[fib.len() - 2];
+			fib.push(next);
+		}
+
+		let unlimited = build_code_lengths(&fib);
+		assert!(unlimited.iter().any(|&l| l > 15), "test fixture should exceed 15 bits unlimited");
+
+		let limited = build_length_limited_code_lengths(&fib, 15);
+		assert!(limited.iter().all(|&l| l <= 15));
+		assert!((kraft_sum(&limited) - 1.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn length_limited_single_symbol_gets_length_one() {
+		let freqs = [0u32, 7, 0];
+		let lengths = build_length_limited_code_lengths(&freqs, 15);
+		assert_eq!(lengths, vec![0, 1, 0]);
+	}
+}
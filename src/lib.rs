@@ -0,0 +1,3 @@
+pub mod deflate;
+mod checksum;
+mod huffman;
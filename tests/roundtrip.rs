@@ -0,0 +1,336 @@
+// End-to-end coverage for the deflate module's public API: every encoder
+// entry point (`deflate`, `gzip`, `zlib`, `Compressor`) is exercised against
+// a small decoder built independently from the spec below, so these tests
+// catch bitstream regressions instead of just "it didn't panic".
+//
+// The decoder intentionally doesn't reuse any of the crate's own Huffman or
+// LZ77 code -- that would let an encoder bug and a matching decoder bug
+// cancel out silently.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use gziper::deflate::{deflate, gzip, zlib, Compressor};
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize, // bit position
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader { data, pos: 0 }
+    }
+
+    fn bits(&mut self, n: u32) -> u32 {
+        let mut v = 0;
+        for i in 0..n {
+            let byte = self.data[self.pos / 8];
+            let bit = (byte >> (self.pos % 8)) & 1;
+            v |= (bit as u32) << i;
+            self.pos += 1;
+        }
+        v
+    }
+
+    fn align_to_byte(&mut self) {
+        self.pos = self.pos.div_ceil(8) * 8;
+    }
+}
+
+// Canonical Huffman decode table, keyed by (code length, code value) in the
+// same bit order `read_symbol` accumulates incoming bits in.
+fn build_tree(lengths: &[u8]) -> HashMap<(u8, u32), usize> {
+    let max_bits = lengths.iter().cloned().max().unwrap_or(0) as usize;
+    let mut bl_count = vec![0u32; max_bits + 1];
+    for &len in lengths {
+        if len > 0 {
+            bl_count[len as usize] += 1;
+        }
+    }
+    let mut next_code = vec![0u32; max_bits + 1];
+    let mut code = 0u32;
+    for bits in 1..=max_bits {
+        code = (code + bl_count[bits - 1]) << 1;
+        next_code[bits] = code;
+    }
+    let mut table = HashMap::new();
+    for (sym, &len) in lengths.iter().enumerate() {
+        if len > 0 {
+            table.insert((len, next_code[len as usize]), sym);
+            next_code[len as usize] += 1;
+        }
+    }
+    table
+}
+
+fn read_symbol(reader: &mut BitReader, table: &HashMap<(u8, u32), usize>) -> usize {
+    let mut code = 0u32;
+    for length in 1..=15u8 {
+        code = (code << 1) | reader.bits(1);
+        if let Some(&sym) = table.get(&(length, code)) {
+            return sym;
+        }
+    }
+    panic!("no matching Huffman code found");
+}
+
+const LEN_TO_CODE: [(u32, u32, u32, usize); 29] = [
+    (3, 4, 0, 257), (4, 5, 0, 258), (5, 6, 0, 259), (6, 7, 0, 260), (7, 8, 0, 261),
+    (8, 9, 0, 262), (9, 10, 0, 263), (10, 11, 0, 264), (11, 13, 1, 265), (13, 15, 1, 266),
+    (15, 17, 1, 267), (17, 19, 1, 268), (19, 23, 2, 269), (23, 27, 2, 270), (27, 31, 2, 271),
+    (31, 35, 2, 272), (35, 43, 3, 273), (43, 51, 3, 274), (51, 59, 3, 275), (59, 67, 3, 276),
+    (67, 83, 4, 277), (83, 99, 4, 278), (99, 115, 4, 279), (115, 131, 4, 280), (131, 163, 5, 281),
+    (163, 195, 5, 282), (195, 227, 5, 283), (227, 258, 5, 284), (258, 259, 0, 285),
+];
+
+const DIST_TO_CODE: [(u32, u32, u32, usize); 30] = [
+    (1, 2, 0, 0), (2, 3, 0, 1), (3, 4, 0, 2), (4, 5, 0, 3), (5, 7, 1, 4), (7, 9, 1, 5),
+    (9, 13, 2, 6), (13, 17, 2, 7), (17, 25, 3, 8), (25, 33, 3, 9), (33, 49, 4, 10),
+    (49, 65, 4, 11), (65, 97, 5, 12), (97, 129, 5, 13), (129, 193, 6, 14), (193, 257, 6, 15),
+    (257, 385, 7, 16), (385, 513, 7, 17), (513, 769, 8, 18), (769, 1025, 8, 19),
+    (1025, 1537, 9, 20), (1537, 2049, 9, 21), (2049, 3073, 10, 22), (3073, 4097, 10, 23),
+    (4097, 6145, 11, 24), (6145, 8193, 11, 25), (8193, 12289, 12, 26), (12289, 16385, 12, 27),
+    (16385, 24577, 13, 28), (24577, 32769, 13, 29),
+];
+
+// A from-scratch, RFC 1951-only raw DEFLATE decoder, for verifying the
+// crate's own encoder output.
+fn inflate(data: &[u8]) -> Vec<u8> {
+    let mut reader = BitReader::new(data);
+    let mut out: Vec<u8> = Vec::new();
+
+    loop {
+        let is_final = reader.bits(1) == 1;
+        let btype = reader.bits(2);
+
+        match btype {
+            0 => {
+                reader.align_to_byte();
+                let len = reader.bits(16) as usize;
+                reader.bits(16); // NLEN, ignored
+                let start = reader.pos / 8;
+                out.extend_from_slice(&reader.data[start..start + len]);
+                reader.pos += len * 8;
+            }
+            1 | 2 => {
+                let (lit_lens, dist_lens) = if btype == 1 {
+                    let mut lit_lens = vec![8u8; 288];
+                    lit_lens[144..256].fill(9);
+                    lit_lens[256..280].fill(7);
+                    (lit_lens, vec![5u8; 30])
+                } else {
+                    read_dynamic_lengths(&mut reader)
+                };
+
+                let lit_table = build_tree(&lit_lens);
+                let dist_table = build_tree(&dist_lens);
+                loop {
+                    let sym = read_symbol(&mut reader, &lit_table);
+                    if sym < 256 {
+                        out.push(sym as u8);
+                    } else if sym == 256 {
+                        break;
+                    } else {
+                        let (len_start, _, extra_bits, _) = LEN_TO_CODE.iter().find(|&&(_, _, _, c)| c == sym).unwrap();
+                        let length = len_start + reader.bits(*extra_bits);
+                        let dsym = read_symbol(&mut reader, &dist_table);
+                        let (dist_start, _, extra_bits, _) = DIST_TO_CODE.iter().find(|&&(_, _, _, c)| c == dsym).unwrap();
+                        let dist = dist_start + reader.bits(*extra_bits);
+                        for _ in 0..length {
+                            let byte = out[out.len() - dist as usize];
+                            out.push(byte);
+                        }
+                    }
+                }
+            }
+            _ => panic!("invalid BTYPE"),
+        }
+
+        if is_final {
+            break;
+        }
+    }
+
+    out
+}
+
+fn read_dynamic_lengths(reader: &mut BitReader) -> (Vec<u8>, Vec<u8>) {
+    let hlit = reader.bits(5) as usize + 257;
+    let hdist = reader.bits(5) as usize + 1;
+    let hclen = reader.bits(4) as usize + 4;
+
+    const ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+    let mut code_len_lens = vec![0u8; 19];
+    for &index in &ORDER[..hclen] {
+        code_len_lens[index] = reader.bits(3) as u8;
+    }
+    let code_len_table = build_tree(&code_len_lens);
+
+    let mut lens = Vec::with_capacity(hlit + hdist);
+    while lens.len() < hlit + hdist {
+        let sym = read_symbol(reader, &code_len_table);
+        match sym {
+            0..=15 => lens.push(sym as u8),
+            16 => {
+                let rep = reader.bits(2) + 3;
+                let prev = *lens.last().unwrap();
+                lens.extend(std::iter::repeat_n(prev, rep as usize));
+            }
+            17 => {
+                let rep = reader.bits(3) + 3;
+                lens.extend(std::iter::repeat_n(0, rep as usize));
+            }
+            18 => {
+                let rep = reader.bits(7) + 11;
+                lens.extend(std::iter::repeat_n(0, rep as usize));
+            }
+            _ => panic!("invalid code-length symbol"),
+        }
+    }
+
+    let mut lit_lens = lens[..hlit].to_vec();
+    lit_lens.resize(286, 0);
+    let mut dist_lens = lens[hlit..].to_vec();
+    dist_lens.resize(30, 0);
+    (lit_lens, dist_lens)
+}
+
+fn gunzip(data: &[u8]) -> Vec<u8> {
+    inflate(&data[10..data.len() - 8])
+}
+
+fn unzlib(data: &[u8]) -> Vec<u8> {
+    inflate(&data[2..data.len() - 4])
+}
+
+// A handful of inputs chosen to exercise different encoder paths: empty,
+// tiny, highly repetitive (favors Dynamic with long repeat tokens),
+// a shifting distribution (exercises block_splitter's greedy split), and
+// pseudo-random (favors Fixed/Stored, few usable matches). `lcg` avoids a
+// `rand` dependency this manifest-less crate doesn't have.
+fn lcg(seed: &mut u64) -> u64 {
+    *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    *seed
+}
+
+fn sample_inputs() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("empty", vec![]),
+        ("one_byte", vec![42]),
+        ("all_same", vec![7u8; 70_000]),
+        ("periodic", (0..70_000u32).map(|i| (i % 251) as u8).collect()),
+        ("text_repeat", {
+            let base = b"the quick brown fox jumps over the lazy dog. ".to_vec();
+            let mut v = Vec::new();
+            while v.len() < 80_000 {
+                v.extend_from_slice(&base);
+            }
+            v
+        }),
+        ("pseudo_random", {
+            let mut seed = 20260730u64;
+            (0..90_000).map(|_| (lcg(&mut seed) >> 33) as u8).collect()
+        }),
+        ("shifting_distribution", {
+            let mut v = vec![b'a'; 50_000];
+            let mut seed = 7u64;
+            v.extend((0..50_000).map(|_| (lcg(&mut seed) >> 33) as u8));
+            v
+        }),
+    ]
+}
+
+#[test]
+fn deflate_round_trips() {
+    for (name, data) in sample_inputs() {
+        let mut out = Vec::new();
+        deflate(&data, &mut out);
+        assert_eq!(inflate(&out), data, "deflate mismatch for {name}");
+    }
+}
+
+#[test]
+fn gzip_round_trips() {
+    for (name, data) in sample_inputs() {
+        let mut out = Vec::new();
+        gzip(&data, &mut out);
+        assert_eq!(out[0..2], [0x1F, 0x8B], "bad gzip magic for {name}");
+        assert_eq!(gunzip(&out), data, "gzip mismatch for {name}");
+    }
+}
+
+#[test]
+fn zlib_round_trips() {
+    for (name, data) in sample_inputs() {
+        let mut out = Vec::new();
+        zlib(&data, &mut out);
+        assert_eq!((((out[0] as u16) * 256 + out[1] as u16) % 31), 0, "bad zlib FCHECK for {name}");
+        assert_eq!(unzlib(&out), data, "zlib mismatch for {name}");
+    }
+}
+
+#[test]
+fn compressor_round_trips_at_various_chunk_sizes() {
+    for (name, data) in sample_inputs() {
+        for &chunk in &[1usize, 7, 500, 4096, usize::MAX] {
+            let chunk = chunk.min(data.len().max(1));
+            let mut out = Vec::new();
+            {
+                let mut comp = Compressor::new(&mut out);
+                for piece in data.chunks(chunk) {
+                    comp.write_all(piece).unwrap();
+                }
+                comp.finish().unwrap();
+            }
+            assert_eq!(inflate(&out), data, "Compressor mismatch for {name} at chunk size {chunk}");
+        }
+    }
+}
+
+#[test]
+fn compressor_gzip_and_zlib_containers_round_trip() {
+    let data = sample_inputs().into_iter().find(|(name, _)| *name == "shifting_distribution").unwrap().1;
+
+    let mut gz_out = Vec::new();
+    {
+        let mut comp = Compressor::gzip(&mut gz_out).unwrap();
+        for piece in data.chunks(777) {
+            comp.write_all(piece).unwrap();
+        }
+        comp.finish().unwrap();
+    }
+    assert_eq!(gunzip(&gz_out), data);
+
+    let mut zlib_out = Vec::new();
+    {
+        let mut comp = Compressor::zlib(&mut zlib_out).unwrap();
+        for piece in data.chunks(4001) {
+            comp.write_all(piece).unwrap();
+        }
+        comp.finish().unwrap();
+    }
+    assert_eq!(unzlib(&zlib_out), data);
+}
+
+// Input bytes with Fibonacci-weighted counts, as a dedicated, non-random
+// regression case. Each byte value comes as one long run, which LZ77 mostly
+// turns into repeat tokens, so the Huffman symbol frequencies deflate builds
+// codes from are not Fibonacci-weighted themselves; the 15-bit limit on such
+// frequencies is covered by the unit test in `huffman`.
+#[test]
+fn deflate_handles_fibonacci_skewed_frequencies() {
+    let mut fib = vec![1u32, 1u32];
+    while fib.len() < 32 {
+        let next = fib[fib.len() - 1] + fib[fib.len() - 2];
+        fib.push(next);
+    }
+    let mut data = Vec::new();
+    for (symbol, &count) in fib.iter().enumerate() {
+        data.extend(std::iter::repeat_n(symbol as u8, count as usize));
+    }
+
+    let mut out = Vec::new();
+    deflate(&data, &mut out);
+    assert_eq!(inflate(&out), data);
+}